@@ -1,13 +1,82 @@
-use crate::{Bytes, Vec};
+use bitflags::bitflags;
 use cosey::EcdhEsHkdf256PublicKey;
 use serde::{Deserialize, Serialize};
 use serde_bytes::ByteArray;
 use serde_indexed::{DeserializeIndexed, SerializeIndexed};
 
+use crate::{Bytes, Vec};
+
 use super::{AttestationFormatsPreference, AttestationStatement, AuthenticatorOptions, Result};
 use crate::sizes::*;
 use crate::webauthn::*;
 
+/// The PIN/UV auth protocol version in use for a `pin_auth`/pinUvAuthToken,
+/// per CTAP 2.1 §6.5.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PinUvAuthProtocol {
+    V1 = 1,
+    V2 = 2,
+}
+
+impl Serialize for PinUvAuthProtocol {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        (*self as u32).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PinUvAuthProtocol {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match u32::deserialize(deserializer)? {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            other => Err(serde::de::Error::custom(format_args!(
+                "unknown PIN/UV auth protocol version {}",
+                other
+            ))),
+        }
+    }
+}
+
+bitflags! {
+    /// The operations a pinUvAuthToken is scoped to, per CTAP 2.1 §6.5.5.7.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct Permissions: u8 {
+        const MAKE_CREDENTIAL = 0x01;
+        const GET_ASSERTION = 0x02;
+        const CREDENTIAL_MANAGEMENT = 0x04;
+        const BIO_ENROLLMENT = 0x08;
+        const LARGE_BLOB_WRITE = 0x10;
+        const AUTHENTICATOR_CONFIGURATION = 0x20;
+    }
+}
+
+impl Serialize for Permissions {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.bits().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Permissions {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let bits = u8::deserialize(deserializer)?;
+        Self::from_bits(bits)
+            .ok_or_else(|| serde::de::Error::custom(format_args!("invalid permissions bits {:#x}", bits)))
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, SerializeIndexed, DeserializeIndexed)]
 #[non_exhaustive]
 #[serde_indexed(offset = 1)]
@@ -17,7 +86,138 @@ pub struct HmacSecretInput {
     pub salt_enc: Bytes<80>,
     pub salt_auth: Bytes<32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub pin_protocol: Option<u32>,
+    pub pin_protocol: Option<PinUvAuthProtocol>,
+}
+
+/// A single fragment of a `largeBlob` read or write, per CTAP 2.1 §6.1.3.
+/// Authenticators advertise the actual maximum fragment length via
+/// `maxFragmentLength` in getInfo; this is just a generous upper bound for
+/// the wire type.
+pub const LARGE_BLOB_MAX_FRAGMENT_LENGTH: usize = 1024;
+
+/// Input to the `largeBlob` extension: either a read request or a write of
+/// one fragment. Exactly one of `read`/`write` is expected to be set.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+pub struct LargeBlobInput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write: Option<Bytes<LARGE_BLOB_MAX_FRAGMENT_LENGTH>>,
+}
+
+/// Maximum length of a WebAuthn PRF evaluation input, per eval point.
+pub const PRF_MAX_EVAL_LENGTH: usize = 256;
+
+/// The label CTAP uses to domain-separate PRF salts from other `hmac-secret`
+/// consumers, per the WebAuthn PRF extension.
+const PRF_SALT_LABEL: &[u8] = b"WebAuthn PRF\x00";
+
+/// The `prf` extension's `eval` inputs, layered over `hmac-secret`: each
+/// input is hashed into a salt that is then encrypted into
+/// `HmacSecretInput::salt_enc`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+pub struct PrfInput {
+    pub first: Bytes<PRF_MAX_EVAL_LENGTH>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub second: Option<Bytes<PRF_MAX_EVAL_LENGTH>>,
+}
+
+#[cfg(feature = "prf")]
+impl PrfInput {
+    /// Derives the plaintext `hmac-secret` salt(s) for this PRF evaluation,
+    /// ready to be encrypted into `HmacSecretInput::salt_enc`: `salt_i =
+    /// SHA-256("WebAuthn PRF" || 0x00 || eval_input_i)`, concatenated when
+    /// `second` is present.
+    pub fn derive_salts(&self) -> Bytes<64> {
+        use sha2::{Digest, Sha256};
+
+        fn hash(eval: &[u8]) -> [u8; 32] {
+            let mut hasher = Sha256::new();
+            hasher.update(PRF_SALT_LABEL);
+            hasher.update(eval);
+            hasher.finalize().into()
+        }
+
+        let mut salts = Bytes::new();
+        salts.extend_from_slice(&hash(&self.first)).ok();
+        if let Some(second) = &self.second {
+            salts.extend_from_slice(&hash(second)).ok();
+        }
+        salts
+    }
+}
+
+#[cfg(all(test, feature = "prf"))]
+mod prf_tests {
+    use super::*;
+
+    // Known-answer vectors: salt_i = SHA-256("WebAuthn PRF" || 0x00 || eval_input_i),
+    // independently computed from the `eval_input` bytes below.
+    const EVAL_FIRST: &[u8] = b"first-eval-input";
+    const SALT_FIRST: [u8; 32] = [
+        0x20, 0xe1, 0x5c, 0x44, 0xcd, 0x93, 0x76, 0x6f, 0x8d, 0xcc, 0xb4, 0xa4, 0xf8, 0x48, 0x37,
+        0x78, 0x56, 0xbf, 0xaa, 0x5b, 0x1e, 0xbb, 0xcd, 0x6f, 0xe9, 0x0f, 0x69, 0x06, 0x8d, 0x93,
+        0xeb, 0x10,
+    ];
+    const EVAL_SECOND: &[u8] = b"second-eval-input";
+    const SALT_SECOND: [u8; 32] = [
+        0x72, 0x3d, 0x15, 0xee, 0x8b, 0x23, 0x6a, 0x07, 0xea, 0x6c, 0xb0, 0xcb, 0x57, 0x29, 0x24,
+        0xf9, 0x05, 0x20, 0x29, 0x1f, 0x48, 0x90, 0xfd, 0xdb, 0x00, 0xe1, 0x9b, 0xa4, 0x2a, 0x19,
+        0x23, 0x96,
+    ];
+
+    #[test]
+    fn derive_salts_single_eval_input() {
+        let input = PrfInput {
+            first: Bytes::from_slice(EVAL_FIRST).unwrap(),
+            second: None,
+        };
+        assert_eq!(input.derive_salts(), Bytes::from_slice(&SALT_FIRST).unwrap());
+    }
+
+    #[test]
+    fn derive_salts_two_eval_inputs() {
+        let input = PrfInput {
+            first: Bytes::from_slice(EVAL_FIRST).unwrap(),
+            second: Some(Bytes::from_slice(EVAL_SECOND).unwrap()),
+        };
+        let mut expected = Bytes::<64>::new();
+        expected.extend_from_slice(&SALT_FIRST).unwrap();
+        expected.extend_from_slice(&SALT_SECOND).unwrap();
+        assert_eq!(input.derive_salts(), expected);
+    }
+}
+
+/// The decrypted `prf` extension result(s), parsed out of the plaintext
+/// recovered from `ExtensionsOutput::hmac_secret`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct PrfOutput {
+    pub first: Bytes<32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub second: Option<Bytes<32>>,
+}
+
+impl PrfOutput {
+    /// Splits the decrypted `hmac-secret` plaintext (32 or 64 bytes) into
+    /// one or two PRF results.
+    pub fn from_hmac_secret_plaintext(plaintext: &[u8]) -> Option<Self> {
+        match plaintext.len() {
+            32 => Some(Self {
+                first: Bytes::from_slice(plaintext).ok()?,
+                second: None,
+            }),
+            64 => Some(Self {
+                first: Bytes::from_slice(&plaintext[..32]).ok()?,
+                second: Some(Bytes::from_slice(&plaintext[32..]).ok()?),
+            }),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -28,17 +228,48 @@ pub struct ExtensionsInput {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hmac_secret: Option<HmacSecretInput>,
 
+    /// The WebAuthn PRF extension, layered over `hmac-secret`.
+    ///
+    /// `"prf"` is a WebAuthn-API-level concept only: no CTAP2 extension
+    /// identifier of that name exists on the wire. A client translates this
+    /// into `hmac_secret.salt_enc` via `PrfInput::derive_salts` before
+    /// sending the request, so this field is never (de)serialized.
+    #[serde(skip)]
+    pub prf: Option<PrfInput>,
+
     /// Whether a large blob key is requested.
     #[serde(rename = "largeBlobKey")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub large_blob_key: Option<bool>,
 
+    /// Whether the stored credBlob (set during makeCredential) should be
+    /// returned alongside the assertion.
+    #[serde(rename = "credBlob")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cred_blob: Option<bool>,
+
+    /// `largeBlob` read/write request, distinct from `largeBlobKey` above.
+    #[serde(rename = "largeBlob")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub large_blob: Option<LargeBlobInput>,
+
     #[cfg(feature = "third-party-payment")]
     #[serde(rename = "thirdPartyPayment")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub third_party_payment: Option<bool>,
 }
 
+/// Unsigned output of the `largeBlob` extension: a read returns the
+/// requested fragment, a write just confirms success.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct LargeBlobOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob: Option<Bytes<LARGE_BLOB_MAX_FRAGMENT_LENGTH>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub written: Option<bool>,
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct ExtensionsOutput {
@@ -47,6 +278,19 @@ pub struct ExtensionsOutput {
     // *either* enc(output1) *or* enc(output1 || output2)
     pub hmac_secret: Option<Bytes<80>>,
 
+    /// The decrypted `prf` results, derived from `hmac_secret` above via
+    /// `PrfOutput::from_hmac_secret_plaintext`. Like `prf` on
+    /// `ExtensionsInput`, this is a client-side-only concept and is never
+    /// (de)serialized as part of the CTAP2 wire format.
+    #[serde(skip)]
+    pub prf: Option<PrfOutput>,
+
+    /// The stored credBlob, copied in verbatim when requested and present.
+    /// See `maxCredBlobLength` in getInfo for the upper bound.
+    #[serde(rename = "credBlob")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cred_blob: Option<Bytes<32>>,
+
     #[cfg(feature = "third-party-payment")]
     #[serde(rename = "thirdPartyPayment")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -58,12 +302,20 @@ impl ExtensionsOutput {
     pub fn is_set(&self) -> bool {
         let Self {
             hmac_secret,
+            prf,
+            cred_blob,
             #[cfg(feature = "third-party-payment")]
             third_party_payment,
         } = self;
         if hmac_secret.is_some() {
             return true;
         }
+        if prf.is_some() {
+            return true;
+        }
+        if cred_blob.is_some() {
+            return true;
+        }
         #[cfg(feature = "third-party-payment")]
         if third_party_payment.is_some() {
             return true;
@@ -85,7 +337,11 @@ pub type AuthenticatorData<'a> =
 
 pub type AllowList<'a> = Vec<PublicKeyCredentialDescriptorRef<'a>, MAX_CREDENTIAL_COUNT_IN_LIST>;
 
+// TODO(client feature): make_credential::Request/Response need the same
+// cfg_attr(feature = "client", derive(...)) treatment; tracked separately
+// since make_credential isn't part of this checkout.
 #[derive(Clone, Debug, Eq, PartialEq, DeserializeIndexed)]
+#[cfg_attr(feature = "client", derive(SerializeIndexed))]
 #[non_exhaustive]
 #[serde_indexed(offset = 1)]
 pub struct Request<'a> {
@@ -100,7 +356,7 @@ pub struct Request<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pin_auth: Option<&'a serde_bytes::Bytes>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub pin_protocol: Option<u32>,
+    pub pin_protocol: Option<PinUvAuthProtocol>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enterprise_attestation: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -111,6 +367,7 @@ pub struct Request<'a> {
 // https://fidoalliance.org/specs/fido-v2.0-ps-20190130/fido-client-to-authenticator-protocol-v2.0-ps-20190130.html#authenticatorMakeCredential
 // does not coincide with what python-fido2 expects in AttestationObject.__init__ *at all* :'-)
 #[derive(Clone, Debug, Eq, PartialEq, SerializeIndexed)]
+#[cfg_attr(feature = "client", derive(DeserializeIndexed))]
 #[non_exhaustive]
 #[serde_indexed(offset = 1)]
 pub struct Response {
@@ -164,6 +421,10 @@ impl ResponseBuilder {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
 #[non_exhaustive]
-pub struct UnsignedExtensionOutputs {}
+pub struct UnsignedExtensionOutputs {
+    #[serde(rename = "largeBlob")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub large_blob: Option<LargeBlobOutput>,
+}
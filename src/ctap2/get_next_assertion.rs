@@ -0,0 +1,179 @@
+use super::{get_assertion, Error, Result};
+
+/// authenticatorGetNextAssertion takes no arguments.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Request;
+
+/// The response shape is identical to getAssertion's.
+pub type Response = get_assertion::Response;
+
+/// Tracks an authenticatorGetNextAssertion sequence opened by a getAssertion
+/// response that sets `number_of_credentials` (a count of 1 gives a sequence
+/// that is already complete), enforcing the invariants from CTAP 2.1 §6.2:
+/// - the count is fixed by the first (getAssertion) response
+/// - `user` must be present on that first response when the count is > 1
+/// - every following (getNextAssertion) response omits `number_of_credentials`
+/// - the sequence must be fully consumed before a new getAssertion starts
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssertionSequence {
+    total: u32,
+    returned: u32,
+}
+
+impl AssertionSequence {
+    /// Starts tracking a sequence from the initial getAssertion response.
+    /// Returns `None` if that response did not open a sequence at all
+    /// (`number_of_credentials` unset).
+    ///
+    /// `previous` must be the sequence (if any) opened by an earlier
+    /// getAssertion call; passing one that isn't `is_complete()` is
+    /// rejected with `Error::NotAllowed`, enforcing that a sequence is
+    /// fully consumed before a new one starts.
+    pub fn start(
+        response: &get_assertion::Response,
+        previous: Option<&Self>,
+    ) -> Result<Option<Self>> {
+        if let Some(previous) = previous {
+            if !previous.is_complete() {
+                return Err(Error::NotAllowed);
+            }
+        }
+        let Some(total) = response.number_of_credentials else {
+            return Ok(None);
+        };
+        if total == 0 {
+            return Err(Error::InvalidParameter);
+        }
+        if total > 1 && response.user.is_none() {
+            return Err(Error::InvalidParameter);
+        }
+        Ok(Some(Self { total, returned: 1 }))
+    }
+
+    /// Validates and records the next getNextAssertion response.
+    pub fn advance(&mut self, response: &Response) -> Result<()> {
+        if self.is_complete() {
+            return Err(Error::NotAllowed);
+        }
+        if response.number_of_credentials.is_some() {
+            return Err(Error::InvalidParameter);
+        }
+        self.returned += 1;
+        Ok(())
+    }
+
+    /// The number of credentials remaining to be returned.
+    pub fn remaining(&self) -> u32 {
+        self.total - self.returned
+    }
+
+    /// Whether every credential in the sequence has been returned. A new
+    /// getAssertion must not be started until this is `true`.
+    pub fn is_complete(&self) -> bool {
+        self.returned >= self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webauthn::PublicKeyCredentialUserEntity;
+
+    fn response(
+        number_of_credentials: Option<u32>,
+        user: Option<PublicKeyCredentialUserEntity>,
+    ) -> get_assertion::Response {
+        get_assertion::Response {
+            credential: Default::default(),
+            auth_data: Default::default(),
+            signature: Default::default(),
+            user,
+            number_of_credentials,
+            user_selected: None,
+            large_blob_key: None,
+            unsigned_extension_outputs: None,
+            ep_att: None,
+            att_stmt: None,
+            response_auth: None,
+        }
+    }
+
+    #[test]
+    fn start_without_count_does_not_open_a_sequence() {
+        let response = response(None, None);
+        assert_eq!(AssertionSequence::start(&response, None).unwrap(), None);
+    }
+
+    #[test]
+    fn start_with_count_one_does_not_require_user() {
+        let response = response(Some(1), None);
+        let sequence = AssertionSequence::start(&response, None).unwrap().unwrap();
+        assert!(sequence.is_complete());
+    }
+
+    #[test]
+    fn start_with_count_above_one_requires_user() {
+        let response = response(Some(2), None);
+        assert!(matches!(
+            AssertionSequence::start(&response, None),
+            Err(Error::InvalidParameter)
+        ));
+    }
+
+    #[test]
+    fn start_with_count_above_one_and_user_succeeds() {
+        let response = response(Some(2), Some(Default::default()));
+        let sequence = AssertionSequence::start(&response, None).unwrap().unwrap();
+        assert!(!sequence.is_complete());
+        assert_eq!(sequence.remaining(), 1);
+    }
+
+    #[test]
+    fn start_rejects_zero_credentials() {
+        let response = response(Some(0), None);
+        assert!(matches!(
+            AssertionSequence::start(&response, None),
+            Err(Error::InvalidParameter)
+        ));
+    }
+
+    #[test]
+    fn start_rejects_an_incomplete_previous_sequence() {
+        let first = response(Some(2), Some(Default::default()));
+        let previous = AssertionSequence::start(&first, None).unwrap().unwrap();
+        let next = response(Some(1), None);
+        assert!(matches!(
+            AssertionSequence::start(&next, Some(&previous)),
+            Err(Error::NotAllowed)
+        ));
+    }
+
+    #[test]
+    fn advance_happy_path() {
+        let first = response(Some(2), Some(Default::default()));
+        let mut sequence = AssertionSequence::start(&first, None).unwrap().unwrap();
+        sequence.advance(&response(None, None)).unwrap();
+        assert!(sequence.is_complete());
+    }
+
+    #[test]
+    fn advance_after_complete_is_rejected() {
+        let first = response(Some(1), None);
+        let mut sequence = AssertionSequence::start(&first, None).unwrap().unwrap();
+        assert!(matches!(
+            sequence.advance(&response(None, None)),
+            Err(Error::NotAllowed)
+        ));
+    }
+
+    #[test]
+    fn advance_rejects_a_response_that_resets_the_count() {
+        let first = response(Some(2), Some(Default::default()));
+        let mut sequence = AssertionSequence::start(&first, None).unwrap().unwrap();
+        assert!(matches!(
+            sequence.advance(&response(Some(1), None)),
+            Err(Error::InvalidParameter)
+        ));
+    }
+}